@@ -0,0 +1,184 @@
+use std::ffi::CString;
+use std::io;
+use std::os::raw::{c_char, c_int, c_void};
+use std::ptr;
+
+use libsqlite3_sys as ffi;
+
+use crate::options::temp::{ChangeAction, HookState};
+use crate::options::SqliteConnectOptions;
+
+use super::SqliteConnection;
+
+/// Open a connection for `options`, registering change-notification hooks and `ATTACH`ing any
+/// sibling databases for the shared [`SqliteTempPath`][crate::options::temp::SqliteTempPath]
+/// it carries, if any.
+pub(crate) fn establish(options: &SqliteConnectOptions) -> io::Result<SqliteConnection> {
+    let mut handle: *mut ffi::sqlite3 = ptr::null_mut();
+    let filename = path_to_cstring(&options.filename)?;
+
+    // SAFETY: `filename` is a valid, NUL-terminated C string that outlives this call, and
+    // `handle` is an out-pointer `sqlite3_open_v2` is documented to always write to.
+    let rc = unsafe {
+        ffi::sqlite3_open_v2(
+            filename.as_ptr(),
+            &mut handle,
+            ffi::SQLITE_OPEN_READWRITE | ffi::SQLITE_OPEN_CREATE | ffi::SQLITE_OPEN_NOMUTEX,
+            ptr::null(),
+        )
+    };
+
+    if rc != ffi::SQLITE_OK {
+        // `handle` may still have been allocated even on failure; `sqlite3_close` on a null
+        // pointer is documented as a harmless no-op.
+        unsafe { ffi::sqlite3_close(handle) };
+        return Err(io::Error::other(format!(
+            "failed to open SQLite database: error code {rc}"
+        )));
+    }
+
+    let hook_state = match &options.temp_path {
+        Some(temp_path) => {
+            let path = temp_path.force_create_blocking()?.to_path_buf();
+            let mut state = Box::new(HookState::new(path));
+
+            // SAFETY: `handle` was just opened above and is closed either by `SqliteConnection`'s
+            // `Drop`, which unregisters the hooks (via `clear_hooks`) before doing so, or below
+            // if an `ATTACH` fails, while `state` is still alive; `state` is heap-allocated and
+            // moves into the returned `SqliteConnection`, so its address stays valid for as
+            // long as `handle` is open.
+            unsafe { register_hooks(handle, &mut state) };
+
+            for statement in temp_path.attach_statements()? {
+                if let Err(err) = exec(handle, &statement) {
+                    unsafe { ffi::sqlite3_close(handle) };
+                    return Err(err);
+                }
+            }
+
+            Some(state)
+        }
+        None => None,
+    };
+
+    Ok(SqliteConnection {
+        handle,
+        hook_state,
+        temp_path: options.temp_path.clone(),
+    })
+}
+
+/// Convert `path` to a NUL-terminated C string for `sqlite3_open_v2`, without lossily replacing
+/// non-UTF-8 bytes -- which on Unix (including an `SQLX_SQLITE_TMPDIR`/`lazy_in` parent outside
+/// our control) would otherwise open or create a different file than the one requested, with no
+/// error raised.
+fn path_to_cstring(path: &std::path::Path) -> io::Result<CString> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStrExt;
+
+        CString::new(path.as_os_str().as_bytes())
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))
+    }
+
+    #[cfg(not(unix))]
+    {
+        let utf8 = path.to_str().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("path {path:?} is not valid UTF-8"),
+            )
+        })?;
+
+        CString::new(utf8).map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))
+    }
+}
+
+/// Execute a statement with no expected rows, e.g. `ATTACH DATABASE`.
+fn exec(handle: *mut ffi::sqlite3, sql: &str) -> io::Result<()> {
+    let sql = CString::new(sql).map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+
+    // SAFETY: `handle` is a valid, open connection and `sql` is a valid, NUL-terminated C
+    // string; we pass no callback or context pointer, so the remaining arguments are unused by
+    // libsqlite3.
+    let rc = unsafe {
+        ffi::sqlite3_exec(handle, sql.as_ptr(), None, ptr::null_mut(), ptr::null_mut())
+    };
+
+    if rc != ffi::SQLITE_OK {
+        return Err(io::Error::other(format!(
+            "failed to execute `{}`: error code {rc}",
+            sql.to_string_lossy()
+        )));
+    }
+
+    Ok(())
+}
+
+/// Register libsqlite3's `update_hook`/`commit_hook`/`rollback_hook` against `handle`, routing
+/// every callback into `state`.
+///
+/// ### Safety
+/// `handle` must be a valid, open connection, and `state` must not be moved or dropped before
+/// [`clear_hooks`] is called against the same `handle` (or `handle` is closed, whichever comes
+/// first) -- libsqlite3 holds its address as an opaque context pointer until then.
+unsafe fn register_hooks(handle: *mut ffi::sqlite3, state: &mut HookState) {
+    let state_ptr = state as *mut HookState as *mut c_void;
+
+    ffi::sqlite3_update_hook(handle, Some(update_hook_trampoline), state_ptr);
+    ffi::sqlite3_commit_hook(handle, Some(commit_hook_trampoline), state_ptr);
+    ffi::sqlite3_rollback_hook(handle, Some(rollback_hook_trampoline), state_ptr);
+}
+
+/// Unregister whatever hooks [`register_hooks`] installed against `handle`.
+///
+/// ### Safety
+/// `handle` must be the same connection passed to [`register_hooks`], and must still be open.
+pub(crate) unsafe fn clear_hooks(handle: *mut ffi::sqlite3) {
+    ffi::sqlite3_update_hook(handle, None, ptr::null_mut());
+    ffi::sqlite3_commit_hook(handle, None, ptr::null_mut());
+    ffi::sqlite3_rollback_hook(handle, None, ptr::null_mut());
+}
+
+unsafe extern "C" fn update_hook_trampoline(
+    state: *mut c_void,
+    action: c_int,
+    database_name: *const c_char,
+    table_name: *const c_char,
+    rowid: i64,
+) {
+    let action = match action {
+        ffi::SQLITE_INSERT => ChangeAction::Insert,
+        ffi::SQLITE_UPDATE => ChangeAction::Update,
+        ffi::SQLITE_DELETE => ChangeAction::Delete,
+        _ => return,
+    };
+
+    // SAFETY: `state` was registered as a `&mut HookState` by `register_hooks` and is kept
+    // alive by the caller's contract until `clear_hooks`/close; libsqlite3 guarantees this
+    // callback only fires on the thread currently inside a statement step on `handle`, so this
+    // is the only live reference to `*state` at a time.
+    let state = unsafe { &mut *(state as *mut HookState) };
+
+    // SAFETY: libsqlite3 guarantees these are valid, NUL-terminated UTF-8 C strings for the
+    // duration of the callback.
+    let database_name = unsafe { std::ffi::CStr::from_ptr(database_name) }.to_string_lossy();
+    let table_name = unsafe { std::ffi::CStr::from_ptr(table_name) }.to_string_lossy();
+
+    state.on_update(action, &database_name, &table_name, rowid);
+}
+
+unsafe extern "C" fn commit_hook_trampoline(state: *mut c_void) -> c_int {
+    // SAFETY: see `update_hook_trampoline`.
+    let state = unsafe { &mut *(state as *mut HookState) };
+    state.on_commit();
+
+    // A non-zero return aborts the commit as if it had failed; we never want that here.
+    0
+}
+
+unsafe extern "C" fn rollback_hook_trampoline(state: *mut c_void) {
+    // SAFETY: see `update_hook_trampoline`.
+    let state = unsafe { &mut *(state as *mut HookState) };
+    state.on_rollback();
+}
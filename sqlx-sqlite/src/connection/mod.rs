@@ -0,0 +1,59 @@
+use std::io;
+
+use libsqlite3_sys as ffi;
+
+mod establish;
+
+pub(crate) use establish::establish;
+
+use crate::options::temp::{ChangeReceiver, HookState, SqliteTempPath};
+
+/// A single open connection to a SQLite database.
+pub struct SqliteConnection {
+    pub(crate) handle: *mut ffi::sqlite3,
+    // Boxed so its address is stable even if `self` moves; libsqlite3 holds a raw pointer to
+    // it for the lifetime of `handle`, set up by `register_hooks` during establishment.
+    hook_state: Option<Box<HookState>>,
+    temp_path: Option<SqliteTempPath>,
+}
+
+// SAFETY: a `sqlite3*` is only ever accessed through `&mut self`, so it is never used from two
+// threads at once; per the SQLite docs, that's all `SQLITE_OPEN_NOMUTEX` (our open flags)
+// requires for a connection handle to be sent between threads.
+unsafe impl Send for SqliteConnection {}
+
+impl SqliteConnection {
+    /// Subscribe to change notifications for the [`SqliteTempPath`] this connection was opened
+    /// from.
+    ///
+    /// Delegates to [`SqliteTempPath::subscribe_changes`]; see that method for details and
+    /// caveats.
+    ///
+    /// ### Errors
+    /// Returns an error if this connection's [`SqliteConnectOptions`][crate::SqliteConnectOptions]
+    /// did not carry a shared [`SqliteTempPath`] via
+    /// [`temp_path`][crate::SqliteConnectOptions::temp_path].
+    pub fn subscribe_changes(&self) -> io::Result<ChangeReceiver> {
+        let temp_path = self.temp_path.as_ref().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::Unsupported,
+                "connection was not opened with a shared `SqliteTempPath`",
+            )
+        })?;
+
+        temp_path.subscribe_changes()
+    }
+}
+
+impl Drop for SqliteConnection {
+    fn drop(&mut self) {
+        // Unregister the hooks first: `hook_state` is about to be dropped, and the trampolines
+        // must not fire against a dangling pointer in the (vanishingly unlikely) window between
+        // that and `sqlite3_close`.
+        if self.hook_state.is_some() {
+            unsafe { establish::clear_hooks(self.handle) };
+        }
+
+        unsafe { ffi::sqlite3_close(self.handle) };
+    }
+}
@@ -0,0 +1,36 @@
+use std::path::{Path, PathBuf};
+
+pub mod temp;
+
+use temp::SqliteTempPath;
+
+#[cfg(doc)]
+use crate::SqliteConnection;
+
+/// Options for opening a new connection to a SQLite database.
+#[derive(Clone)]
+pub struct SqliteConnectOptions {
+    pub(crate) filename: PathBuf,
+    pub(crate) temp_path: Option<SqliteTempPath>,
+}
+
+impl SqliteConnectOptions {
+    /// Create options that connect to the database file at `filename`.
+    pub fn new(filename: impl AsRef<Path>) -> Self {
+        Self {
+            filename: filename.as_ref().to_path_buf(),
+            temp_path: None,
+        }
+    }
+
+    /// Share a [`SqliteTempPath`] handle with every [`SqliteConnection`] opened from these
+    /// options.
+    ///
+    /// Each connection registers libsqlite3's change-notification hooks against the shared
+    /// handle and runs `ATTACH DATABASE` for every alias registered via
+    /// [`SqliteTempPath::attach`] during establishment; see those for details.
+    pub fn temp_path(mut self, temp_path: SqliteTempPath) -> Self {
+        self.temp_path = Some(temp_path);
+        self
+    }
+}
@@ -1,10 +1,18 @@
+use std::collections::HashMap;
 use std::fmt::{Debug, Formatter};
 use std::io;
-use std::path::Path;
-use std::sync::Arc;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
 
 use once_cell::sync::OnceCell;
 
+mod notify;
+
+pub use notify::{ChangeAction, ChangeEvent, ChangeReceiver};
+
+pub(crate) use notify::HookState;
+
 #[cfg(doc)]
 use {
     crate::{SqliteConnectOptions, SqliteConnection}
@@ -16,18 +24,84 @@ use {
 /// created by SQLite, will be deleted when the last handle is dropped.
 ///
 /// [`SqliteConnectOptions`] will retain a handle, as well as any [`SqliteConnection`]s it creates.
+///
+/// Deletion is skipped if [`persist`][Self::persist] or [`keep_on_drop`][Self::keep_on_drop]
+/// marked the directory to be retained.
+///
+/// Connections sharing a handle can observe each other's writes without polling by calling
+/// [`subscribe_changes`][Self::subscribe_changes]; see that method for details and caveats.
 #[derive(Clone)]
 pub struct SqliteTempPath {
     inner: Arc<OnceCell<tempfile::TempDir>>,
+    keep_on_drop: Arc<AtomicBool>,
+    parent: Option<PathBuf>,
+    attached: Arc<Mutex<HashMap<String, String>>>,
+    guard: Option<Arc<dyn SqliteTempPathGuard>>,
 }
 
+impl Debug for SqliteTempPath {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SqliteTempPath")
+            .field("path", &self.inner.get().map(tempfile::TempDir::path))
+            .field("parent", &self.parent)
+            .field("keep_on_drop", &self.keep_on_drop.load(Ordering::Relaxed))
+            .field("guard", &self.guard.is_some())
+            .finish()
+    }
+}
+
+/// Consulted by [`force_create_blocking`][SqliteTempPath::force_create_blocking] before it
+/// creates the temporary directory, letting a sandboxed or multi-tenant host veto or audit
+/// where scratch SQLite databases land, like the `check_read`/`check_write` permission trait in
+/// Deno's `SqliteDbHandler`.
+pub trait SqliteTempPathGuard: Send + Sync {
+    /// Called with the resolved parent directory -- the one passed to
+    /// [`lazy_in`][SqliteTempPath::lazy_in]/[`create_in`][SqliteTempPath::create_in], the
+    /// [`SQLX_SQLITE_TMPDIR_ENV`] override, or the platform's standard temp directory, in that
+    /// order -- immediately before a directory is created under it.
+    ///
+    /// Return an `Err` to abort creation with a clear error instead of silently writing there.
+    fn check_create(&self, parent: &Path) -> io::Result<()>;
+}
+
+/// Environment variable consulted as the default parent directory for new temp directories
+/// when neither [`SqliteTempPath::lazy_in`] nor [`SqliteTempPath::create_in`] was used to set
+/// one explicitly. Unset, [`SqliteTempPath::lazy`]/[`SqliteTempPath::create`] fall back to the
+/// platform's standard temp directory, same as before this variable existed.
+pub const SQLX_SQLITE_TMPDIR_ENV: &str = "SQLX_SQLITE_TMPDIR";
+
 struct TempDbPath {}
 
+/// Reject anything but a plain identifier, so [`SqliteTempPath::attach`] can never be made to
+/// write outside its handle's directory via `/`, `..`, or an absolute path.
+fn validate_alias(alias: &str) -> io::Result<()> {
+    let is_plain_identifier = !alias.is_empty()
+        && alias
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+
+    if is_plain_identifier {
+        Ok(())
+    } else {
+        Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "invalid SqliteTempPath alias {alias:?}: must be non-empty and contain only \
+                 ASCII letters, digits, '_', or '-'"
+            ),
+        ))
+    }
+}
+
 impl SqliteTempPath {
     /// Create a handle that will lazily create the temporary directory on first connection.
     pub fn lazy() -> Self {
         Self {
-            inner: Arc::new(OnceCell::new())
+            inner: Arc::new(OnceCell::new()),
+            keep_on_drop: Arc::new(AtomicBool::new(false)),
+            parent: None,
+            attached: Arc::new(Mutex::new(HashMap::new())),
+            guard: None,
         }
     }
 
@@ -43,15 +117,53 @@ impl SqliteTempPath {
         Ok(this)
     }
 
+    /// Like [`lazy`][Self::lazy], but rooted under `parent` instead of the platform's standard
+    /// temp directory (or the [`SQLX_SQLITE_TMPDIR_ENV`] override, if set) -- e.g. an XDG data
+    /// dir, a `tmpfs` mount, or a per-project scratch folder.
+    pub fn lazy_in(parent: impl Into<PathBuf>) -> Self {
+        Self {
+            inner: Arc::new(OnceCell::new()),
+            keep_on_drop: Arc::new(AtomicBool::new(false)),
+            parent: Some(parent.into()),
+            attached: Arc::new(Mutex::new(HashMap::new())),
+            guard: None,
+        }
+    }
+
+    /// Like [`create`][Self::create], but rooted under `parent`; see [`lazy_in`][Self::lazy_in].
+    ///
+    /// ### Panics
+    /// If no runtime is available.
+    pub async fn create_in(parent: impl Into<PathBuf>) -> io::Result<Self> {
+        let this = Self::lazy_in(parent);
+        this.force_create().await?;
+        Ok(this)
+    }
+
     /// Create a handle from a custom [`tempfile::TempDir`].
     ///
     ///
     pub fn from_tempdir(tempdir: tempfile::TempDir) -> Self {
         Self {
             inner: Arc::new(OnceCell::with_value(tempdir)),
+            keep_on_drop: Arc::new(AtomicBool::new(false)),
+            parent: None,
+            attached: Arc::new(Mutex::new(HashMap::new())),
+            guard: None,
         }
     }
 
+    /// Install a [`SqliteTempPathGuard`] for this handle, consulted by
+    /// [`force_create_blocking`][Self::force_create_blocking] before the temporary directory is
+    /// created.
+    ///
+    /// Typically chained onto [`lazy`][Self::lazy]/[`lazy_in`][Self::lazy_in] before the
+    /// directory has been created; if it already has been, the guard is simply never consulted.
+    pub fn with_guard(mut self, guard: impl SqliteTempPathGuard + 'static) -> Self {
+        self.guard = Some(Arc::new(guard));
+        self
+    }
+
     /// Create a temporary directory for this handle immediately, returning the created path.
     ///
     /// If the directory has already been created, this simply returns the path.
@@ -78,12 +190,160 @@ impl SqliteTempPath {
     /// Create a temporary directory for this handle immediately, returning the created path.
     ///
     /// If the directory has already been created, this simply returns the path.
+    ///
+    /// The directory is created under the parent passed to [`lazy_in`][Self::lazy_in]/
+    /// [`create_in`][Self::create_in], if any; otherwise under the path in
+    /// [`SQLX_SQLITE_TMPDIR_ENV`], if set; otherwise under the platform's standard temp
+    /// directory.
+    ///
+    /// If a [`SqliteTempPathGuard`] was installed via [`with_guard`][Self::with_guard], it is
+    /// consulted with the resolved parent directory first; its error, if any, is returned
+    /// without creating anything.
     pub fn force_create_blocking(&self) -> io::Result<&Path> {
         self.inner.get_or_try_init(|| {
-            tempfile::Builder::new()
-                .prefix("sqlx-sqlite")
-                .suffix(".db")
-                .tempdir()
+            let parent = self.parent().unwrap_or_else(std::env::temp_dir);
+
+            if let Some(guard) = &self.guard {
+                guard.check_create(&parent)?;
+            }
+
+            let mut builder = tempfile::Builder::new();
+            builder.prefix("sqlx-sqlite").suffix(".db");
+            builder.tempdir_in(parent)
         })
     }
+
+    /// The configured parent directory for this handle, if any was set explicitly via
+    /// [`lazy_in`][Self::lazy_in]/[`create_in`][Self::create_in], falling back to
+    /// [`SQLX_SQLITE_TMPDIR_ENV`] if that's set instead.
+    fn parent(&self) -> Option<PathBuf> {
+        self.parent
+            .clone()
+            .or_else(|| std::env::var_os(SQLX_SQLITE_TMPDIR_ENV).map(PathBuf::from))
+    }
+
+    /// Subscribe to change notifications for databases created from this handle.
+    ///
+    /// Every [`SqliteConnection`] established from a [`SqliteConnectOptions`] carrying this
+    /// handle registers libsqlite3's `update_hook`/`commit_hook`/`rollback_hook` against the
+    /// same handle, so any row inserted, updated or deleted by one connection is broadcast as a
+    /// [`ChangeEvent`] to every receiver returned here -- letting readers react to a writer's
+    /// commits instead of polling.
+    ///
+    /// ### Caveats
+    /// Like the notifier map in Deno's SQLite KV backend that inspired this, this only observes
+    /// writes made by `SqliteConnection`s in the *current process* that share this handle. It
+    /// does not see writes made by other processes attached to the same file over WAL, nor
+    /// does it replay history: a subscriber only receives events committed after it subscribed.
+    ///
+    /// Hook registration is serialized through this handle's shared `Arc`, so the underlying
+    /// notifier is torn down automatically once the last connection (and the last subscriber)
+    /// sharing it has been dropped.
+    pub fn subscribe_changes(&self) -> io::Result<ChangeReceiver> {
+        let path = self.force_create_blocking()?;
+        Ok(notify::subscribe(path))
+    }
+
+    /// Disarm deletion and hand ownership of the directory over to the caller.
+    ///
+    /// Ensures the directory has been created, then marks it to be retained (not deleted) by
+    /// [`TempDir::into_path()`][tempfile::TempDir::into_path] once the last handle sharing this
+    /// one is dropped, and returns its path. Useful for keeping a scratch database around after
+    /// a failed test run for inspection, or for "graduating" one into a permanent file.
+    ///
+    /// Note that the actual disarming happens on drop, so it takes effect even if other clones
+    /// of this handle (e.g. held by a live [`SqliteConnectOptions`] or [`SqliteConnection`])
+    /// are still around; the directory simply won't be deleted once they all go away.
+    pub fn persist(self) -> io::Result<PathBuf> {
+        let path = self.force_create_blocking()?.to_path_buf();
+        self.keep_on_drop(true);
+        Ok(path)
+    }
+
+    /// Set whether the directory should be retained (not deleted) once the last handle sharing
+    /// this one is dropped.
+    ///
+    /// Unlike [`persist`][Self::persist], this does not consume the handle, so e.g. test code
+    /// can flip it conditionally -- only keep the directory if the test panics -- without
+    /// restructuring who owns the shared `Arc`.
+    pub fn keep_on_drop(&self, keep: bool) {
+        self.keep_on_drop.store(keep, Ordering::Relaxed);
+    }
+
+    /// Register a sibling database file named `<alias>.db` in this handle's directory and
+    /// return its path.
+    ///
+    /// Every [`SqliteConnection`] established from a [`SqliteConnectOptions`] carrying this
+    /// handle runs `ATTACH DATABASE '<path>' AS <alias>` for each registered alias during
+    /// connection establishment, giving all connections sharing this handle cross-database
+    /// `JOIN`s over the sibling files -- with lifetime tied to the same directory cleanup as
+    /// the primary database.
+    ///
+    /// ### Errors
+    /// Returns an error if `alias` is empty or contains anything other than ASCII letters,
+    /// digits, or `_`/`-`. This is stricter than SQLite's own identifier rules, but it rules
+    /// out `/`, `..`, and absolute paths outright, so the sibling file can never land outside
+    /// this handle's directory.
+    pub fn attach(&self, alias: &str) -> io::Result<PathBuf> {
+        validate_alias(alias)?;
+
+        let dir = self.force_create_blocking()?;
+        let file_name = format!("{alias}.db");
+        let path = dir.join(&file_name);
+
+        self.attached
+            .lock()
+            .unwrap()
+            .insert(alias.to_owned(), file_name);
+
+        Ok(path)
+    }
+
+    /// Build the `ATTACH DATABASE` statements for every alias registered via
+    /// [`attach`][Self::attach].
+    ///
+    /// Called during connection establishment for every [`SqliteConnection`] built from
+    /// [`SqliteConnectOptions`] carrying this handle.
+    ///
+    /// The path is single-quoted with embedded `'` doubled, and the alias is double-quoted as
+    /// an identifier with embedded `"` doubled, so a parent directory or alias chosen by an
+    /// untrusted caller can't break out of the literal/identifier it's placed in.
+    pub(crate) fn attach_statements(&self) -> io::Result<Vec<String>> {
+        let dir = self.force_create_blocking()?;
+
+        Ok(self
+            .attached
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(alias, file_name)| {
+                let path = dir.join(file_name).display().to_string().replace('\'', "''");
+                let alias = alias.replace('"', "\"\"");
+                format!("ATTACH DATABASE '{path}' AS \"{alias}\"")
+            })
+            .collect())
+    }
+}
+
+impl Drop for SqliteTempPath {
+    fn drop(&mut self) {
+        // Only the last handle sharing this `Arc` runs this; if the directory was never
+        // created there's nothing registered in the notifier map to clean up.
+        let Some(inner) = Arc::get_mut(&mut self.inner) else {
+            return;
+        };
+
+        let Some(tempdir) = inner.get() else {
+            return;
+        };
+
+        notify::unregister_if_idle(tempdir.path());
+
+        if self.keep_on_drop.load(Ordering::Relaxed) {
+            if let Some(tempdir) = inner.take() {
+                // Disarm `TempDir`'s own `Drop` impl, leaving the directory on disk.
+                let _ = tempdir.into_path();
+            }
+        }
+    }
 }
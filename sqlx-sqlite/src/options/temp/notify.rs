@@ -0,0 +1,179 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use once_cell::sync::OnceCell;
+use tokio::sync::broadcast;
+
+/// A single row-level write observed through SQLite's `update_hook`.
+///
+/// One [`ChangeEvent`] is emitted per row touched by an `INSERT`, `UPDATE` or `DELETE`
+/// statement, batched per-transaction and only delivered once that transaction commits.
+#[derive(Debug, Clone)]
+pub struct ChangeEvent {
+    pub action: ChangeAction,
+    pub database_name: String,
+    pub table_name: String,
+    pub rowid: i64,
+}
+
+/// The kind of write that produced a [`ChangeEvent`], mirroring `sqlite3_update_hook`'s
+/// `SQLITE_INSERT` / `SQLITE_UPDATE` / `SQLITE_DELETE` action codes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeAction {
+    Insert,
+    Update,
+    Delete,
+}
+
+/// A [`broadcast::Receiver`] returned by
+/// [`SqliteTempPath::subscribe_changes`][super::SqliteTempPath::subscribe_changes].
+///
+/// Behaves like the [`broadcast::Receiver`] it wraps (via [`Deref`]/[`DerefMut`]), but also runs
+/// [`unregister_if_idle`] for its path on drop. Without this, a receiver outliving every
+/// [`SqliteTempPath`][super::SqliteTempPath] sharing its directory would leave that directory's
+/// notifier registered in [`NOTIFIERS`] for the rest of the process's life, since
+/// [`unregister_if_idle`] otherwise only runs from [`SqliteTempPath`][super::SqliteTempPath]'s
+/// own `Drop`.
+pub struct ChangeReceiver {
+    path: PathBuf,
+    receiver: broadcast::Receiver<ChangeEvent>,
+}
+
+impl std::ops::Deref for ChangeReceiver {
+    type Target = broadcast::Receiver<ChangeEvent>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.receiver
+    }
+}
+
+impl std::ops::DerefMut for ChangeReceiver {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.receiver
+    }
+}
+
+impl Drop for ChangeReceiver {
+    fn drop(&mut self) {
+        // `Drop::drop` runs before the fields are dropped, so release our own receiver first;
+        // otherwise `unregister_if_idle` would still count it and never remove the entry.
+        drop(std::mem::replace(
+            &mut self.receiver,
+            broadcast::channel(1).1,
+        ));
+        unregister_if_idle(&self.path);
+    }
+}
+
+// Channel depth before a lagging subscriber starts missing events and gets
+// `RecvError::Lagged` on its next `recv()` instead.
+const CHANNEL_CAPACITY: usize = 256;
+
+type NotifierMap = HashMap<PathBuf, broadcast::Sender<ChangeEvent>>;
+
+static NOTIFIERS: OnceCell<Mutex<NotifierMap>> = OnceCell::new();
+
+fn registry() -> &'static Mutex<NotifierMap> {
+    NOTIFIERS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Subscribe to change notifications for the canonical database path.
+///
+/// Registers a new broadcast channel for `path` if one does not already exist.
+pub(crate) fn subscribe(path: &Path) -> ChangeReceiver {
+    let receiver = registry()
+        .lock()
+        .unwrap()
+        .entry(path.to_path_buf())
+        .or_insert_with(|| broadcast::channel(CHANNEL_CAPACITY).0)
+        .subscribe();
+
+    ChangeReceiver {
+        path: path.to_path_buf(),
+        receiver,
+    }
+}
+
+/// Publish a batch of changes collected by a single transaction's `commit_hook`.
+///
+/// If nobody is subscribed for `path`, this is a no-op: sending on a channel with no
+/// receivers is not an error here, it just means nobody is watching yet.
+pub(crate) fn publish(path: &Path, events: Vec<ChangeEvent>) {
+    let map = registry().lock().unwrap();
+
+    if let Some(sender) = map.get(path) {
+        for event in events {
+            // `send` only errors when every receiver has been dropped, which is
+            // equivalent to "nobody is listening" for our purposes.
+            let _ = sender.send(event);
+        }
+    }
+}
+
+/// Remove the notifier for `path` once nobody holds a sender or receiver for it.
+///
+/// Called both when the last handle sharing a [`super::SqliteTempPath`] for `path` is dropped
+/// and when a [`ChangeReceiver`] for it is dropped -- whichever happens last actually removes
+/// the entry -- so the map doesn't grow unboundedly over the life of the process regardless of
+/// which kind of owner outlives the other.
+pub(crate) fn unregister_if_idle(path: &Path) {
+    let mut map = registry().lock().unwrap();
+
+    if map.get(path).is_some_and(|sender| sender.receiver_count() == 0) {
+        map.remove(path);
+    }
+}
+
+/// Per-connection buffer bridging libsqlite3's `update_hook`/`commit_hook`/`rollback_hook`
+/// callbacks to the path-keyed broadcast channels in [`NOTIFIERS`].
+///
+/// SQLite invokes `update_hook` once per row touched by a write, but those writes are only
+/// durable (and only worth telling anyone about) once the enclosing transaction commits, so
+/// events are buffered here and flushed on `commit_hook` (or dropped on `rollback_hook`).
+#[derive(Default)]
+pub(crate) struct HookState {
+    path: Option<PathBuf>,
+    buffer: Vec<ChangeEvent>,
+}
+
+impl HookState {
+    pub(crate) fn new(path: PathBuf) -> Self {
+        Self {
+            path: Some(path),
+            buffer: Vec::new(),
+        }
+    }
+
+    /// Called from the `sqlite3_update_hook` callback for every row touched by a write.
+    pub(crate) fn on_update(
+        &mut self,
+        action: ChangeAction,
+        database_name: &str,
+        table_name: &str,
+        rowid: i64,
+    ) {
+        self.buffer.push(ChangeEvent {
+            action,
+            database_name: database_name.to_owned(),
+            table_name: table_name.to_owned(),
+            rowid,
+        });
+    }
+
+    /// Called from the `sqlite3_commit_hook` callback; flushes whatever was buffered.
+    pub(crate) fn on_commit(&mut self) {
+        if self.buffer.is_empty() {
+            return;
+        }
+
+        if let Some(path) = &self.path {
+            publish(path, std::mem::take(&mut self.buffer));
+        }
+    }
+
+    /// Called from the `sqlite3_rollback_hook` callback; discards whatever was buffered.
+    pub(crate) fn on_rollback(&mut self) {
+        self.buffer.clear();
+    }
+}